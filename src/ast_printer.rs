@@ -0,0 +1,52 @@
+use crate::expr::Expr;
+use crate::value::Value;
+
+// Prints expressions in the canonical parenthesized form, e.g. `(* (- 5.0) (group 3.0))`
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn print(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Binary(left, operator, right) => self.parenthesize(&operator.text, &[left, right]),
+            Expr::Grouping(expr) => self.parenthesize("group", &[expr]),
+            Expr::Literal(value) => match value {
+                // Numbers keep their fraction (`2.0`), unlike Value's Display
+                Value::Number(n) => format!("{:?}", n),
+                value => value.to_string(),
+            },
+            Expr::Unary(operator, right) => self.parenthesize(&operator.text, &[right]),
+            Expr::Variable(name) => name.text.clone(),
+        }
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut out = format!("({}", name);
+        for expr in exprs {
+            out.push(' ');
+            out.push_str(&self.print(expr));
+        }
+        out.push(')');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::Scanner;
+
+    fn print(source: &str) -> String {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let expr = Parser::new(scanner.into_tokens()).parse().unwrap();
+        AstPrinter.print(&expr)
+    }
+
+    #[test]
+    fn prints_nested_expressions() {
+        assert_eq!(print("1 + 2 * 3"), "(+ 1.0 (* 2.0 3.0))");
+        assert_eq!(print("-(\"hi\") == !nil"), "(== (- (group hi)) (! nil))");
+        assert_eq!(print("true != false"), "(!= true false)");
+    }
+}
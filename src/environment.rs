@@ -0,0 +1,23 @@
+use std::collections::HashMap;
+use crate::value::Value;
+
+// Global variable bindings, keyed by name
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    // Redefining an existing variable simply replaces its value
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+}
@@ -0,0 +1,11 @@
+use crate::Token;
+use crate::value::Value;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Binary(Box<Expr>, Token, Box<Expr>),
+    Grouping(Box<Expr>),
+    Literal(Value),
+    Unary(Token, Box<Expr>),
+    Variable(Token),
+}
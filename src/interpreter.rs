@@ -0,0 +1,182 @@
+use std::fmt::{Display, Formatter};
+use crate::{Token, TokenType};
+use crate::environment::Environment;
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    token: Token,
+    message: String,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.token.line)
+    }
+}
+
+#[derive(Default)]
+pub struct Interpreter {
+    environment: Environment,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::default()
+    }
+
+    // Runs statements in order, stopping at the first runtime error
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        statements.iter().try_for_each(|statement| self.execute(statement))
+    }
+
+    fn execute(&mut self, statement: &Stmt) -> Result<(), RuntimeError> {
+        match statement {
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Print(expr) => println!("{}", self.evaluate(expr)?),
+            Stmt::Var(name, initializer) => {
+                let value = match initializer {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                self.environment.define(&name.text, value);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Unary(operator, right) => {
+                let right = self.evaluate(right)?;
+                match operator.token_type {
+                    TokenType::BANG => Ok(Value::Boolean(!right.is_truthy())),
+                    TokenType::MINUS => match right {
+                        Value::Number(n) => Ok(Value::Number(-n)),
+                        _ => Err(Interpreter::error(operator, "Operand must be a number.")),
+                    },
+                    _ => unreachable!("parser only produces ! and - unary operators"),
+                }
+            }
+            Expr::Binary(left, operator, right) => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary(operator, left, right)
+            }
+            Expr::Variable(name) => match self.environment.get(&name.text) {
+                Some(value) => Ok(value.clone()),
+                None => Err(Interpreter::error(name, &format!("Undefined variable '{}'.", name.text))),
+            },
+        }
+    }
+
+    fn binary(&self, operator: &Token, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        match operator.token_type {
+            TokenType::EQUAL_EQUAL => return Ok(Value::Boolean(left == right)),
+            TokenType::BANG_EQUAL => return Ok(Value::Boolean(left != right)),
+            TokenType::PLUS => {
+                return match (left, right) {
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                    (Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+                    _ => Err(Interpreter::error(operator, "Operands must be two numbers or two strings.")),
+                };
+            }
+            _ => {}
+        }
+
+        // Everything else is arithmetic or comparison over two numbers
+        let (Value::Number(a), Value::Number(b)) = (left, right) else {
+            return Err(Interpreter::error(operator, "Operands must be numbers."));
+        };
+        match operator.token_type {
+            TokenType::MINUS => Ok(Value::Number(a - b)),
+            TokenType::STAR => Ok(Value::Number(a * b)),
+            TokenType::SLASH if b == 0.0 => Err(Interpreter::error(operator, "Division by zero.")),
+            TokenType::SLASH => Ok(Value::Number(a / b)),
+            TokenType::GREATER => Ok(Value::Boolean(a > b)),
+            TokenType::GREATER_EQUAL => Ok(Value::Boolean(a >= b)),
+            TokenType::LESS => Ok(Value::Boolean(a < b)),
+            TokenType::LESS_EQUAL => Ok(Value::Boolean(a <= b)),
+            _ => unreachable!("parser only produces known binary operators"),
+        }
+    }
+
+    fn error(token: &Token, message: &str) -> RuntimeError {
+        RuntimeError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::Scanner;
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), RuntimeError> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let statements = Parser::new(scanner.into_tokens()).parse_program().unwrap();
+        interpreter.interpret(&statements)
+    }
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let expr = Parser::new(scanner.into_tokens()).parse().unwrap();
+        Interpreter::new().evaluate(&expr)
+    }
+
+    #[test]
+    fn evaluates_arithmetic() {
+        assert_eq!(evaluate("(1 + 2) * 3 - 4 / 2").unwrap(), Value::Number(7.0));
+        assert_eq!(evaluate("-(2.5 * 2)").unwrap(), Value::Number(-5.0));
+    }
+
+    #[test]
+    fn concatenates_strings() {
+        assert_eq!(evaluate("\"foo\" + \"bar\"").unwrap(), Value::String(String::from("foobar")));
+    }
+
+    #[test]
+    fn treats_only_nil_and_false_as_falsey() {
+        assert_eq!(evaluate("!nil").unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate("!false").unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate("!0").unwrap(), Value::Boolean(false));
+        assert_eq!(evaluate("!\"\"").unwrap(), Value::Boolean(false));
+    }
+
+    #[test]
+    fn rejects_division_by_zero() {
+        let error = evaluate("1 / 0").unwrap_err();
+        assert_eq!(error.to_string(), "Division by zero.\n[line 1]");
+    }
+
+    #[test]
+    fn rejects_arithmetic_on_non_numbers() {
+        let error = evaluate("\n\n\"a\" * 3").unwrap_err();
+        assert_eq!(error.to_string(), "Operands must be numbers.\n[line 3]");
+    }
+
+    #[test]
+    fn defines_and_reads_variables() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "var x = 1; var y; x + 2;").unwrap();
+        assert_eq!(interpreter.environment.get("x"), Some(&Value::Number(1.0)));
+        assert_eq!(interpreter.environment.get("y"), Some(&Value::Nil));
+    }
+
+    #[test]
+    fn rejects_undefined_variables() {
+        let error = run(&mut Interpreter::new(), "var a = 1;\nprint b;").unwrap_err();
+        assert_eq!(error.to_string(), "Undefined variable 'b'.\n[line 2]");
+    }
+}
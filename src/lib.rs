@@ -0,0 +1,12 @@
+pub mod ast_printer;
+pub mod environment;
+pub mod expr;
+pub mod interpreter;
+pub mod parser;
+pub mod scanner;
+pub mod stmt;
+pub mod value;
+
+pub use scanner::{
+    preprocess, Error, ErrorType, Literal, Scanner, Segmentation, Span, Token, TokenType, JSON_SCHEMA_VERSION, TOKENS,
+};
@@ -1,432 +1,492 @@
-use std::string::String;
 use std::env;
 use std::fs;
 use std::io::{self, Read, Write};
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
 use std::process::exit;
-use std::sync::LazyLock;
-use unicode_segmentation::{Graphemes, UnicodeSegmentation};
-use crate::TokenType::PLUS;
+use codecrafters_interpreter::{preprocess, Error, Scanner, Segmentation, Token, JSON_SCHEMA_VERSION, TOKENS};
+use codecrafters_interpreter::ast_printer::AstPrinter;
+use codecrafters_interpreter::interpreter::Interpreter;
+use codecrafters_interpreter::parser::Parser;
 
-#[derive(Debug, Clone)]
-enum Literal {
-    String(String),
-    Number(f64),
-    Identifier(String),
-    Keyword(String),
-    NULL
+// How tokenize presents the token stream; errors always go to stderr
+enum TokenListing {
+    Full,
+    OmitLiterals,
+    Lines,
+    Pipe,
+    Json,
+    DocComments,
+    Count,
+    Interleaved,
+    None,
 }
 
-impl Display for Literal {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Literal::String(s) => write!(f, "{}", s),
-            Literal::Number(n) => write!(f, "{:?}", n),
-            Literal::Identifier(i) => write!(f, "null"),
-            Literal::Keyword(k) => write!(f, "null"),
-            Literal::NULL => write!(f, "null"),
+const USAGE: &str = "repl | (tokenize | parse | evaluate | run) [--only-errors] [--segmentation grapheme|scalar] [--start-line N] [--emit-lines] [--max-line-length N] [--check-delimiters] [--pipe] [--json] [--omit-literals] [--define FLAG]... [--doc-comments] [--count] [--keep-whitespace] [--interleave] [--stats] [--max-errors N] [--tab-width N] (<filename> | - | -c SOURCE)";
+
+// Evaluates one expression per line until EOF (Ctrl-D). Errors are reported and the loop carries on.
+fn repl() {
+    let mut interpreter = Interpreter::new();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        line.clear();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            println!();
+            return;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut scanner = Scanner::new(line.trim_end().to_string());
+        scanner.scan_tokens();
+        if scanner.has_errors {
+            scanner.errors.iter().for_each(|e| eprintln!("[line {}:{}] Error: {}", e.line, e.column, e.error_type));
+            continue;
+        }
+        match Parser::new(scanner.into_tokens()).parse() {
+            Ok(expr) => match interpreter.evaluate(&expr) {
+                Ok(value) => println!("{}", value),
+                Err(e) => eprintln!("{}", e),
+            },
+            Err(e) => eprintln!("{}", e),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-enum ErrorType {
-    UnexpectedCharacter(String),
-    UnterminatedString(String)
+// `TYPE lexeme` for every token, without the literal column
+fn omit_literals_listing(tokens: &[Token]) -> Vec<String> {
+    tokens.iter().map(|t| format!("{:?} {}", t.token_type, t.text)).collect()
 }
 
-impl Display for ErrorType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ErrorType::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
-            ErrorType::UnterminatedString(s) => write!(f, "Unterminated string."),
-        }
+// `TYPE N` per token type, most frequent first; ties are broken by name so the output is reproducible
+fn count_listing(tokens: &[Token]) -> Vec<String> {
+    let mut histogram: HashMap<String, usize> = HashMap::new();
+    for token in tokens {
+        *histogram.entry(format!("{:?}", token.token_type)).or_default() += 1;
     }
+    let mut counts: Vec<(String, usize)> = histogram.into_iter().collect();
+    counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+    counts.into_iter().map(|(name, count)| format!("{} {}", name, count)).collect()
 }
 
-#[derive(Debug, Clone)]
-struct Error {
-    error_type: ErrorType,
-    line: usize,
+// `line N: TYPE TYPE ...` for every token but EOF, grouped by the line it appears on
+fn line_listing(tokens: &[Token]) -> Vec<String> {
+    let (_eof, tokens) = tokens.split_last().unwrap();
+    tokens
+        .chunk_by(|a, b| a.line == b.line)
+        .map(|line| {
+            let types: Vec<String> = line.iter().map(|t| format!("{:?}", t.token_type)).collect();
+            format!("line {}: {}", line[0].line, types.join(" "))
+        })
+        .collect()
 }
 
-#[derive(Debug, Clone)]
-struct Token {
-    token_type: TokenType,
-    literal: Option<Literal>, // The value as represented internally
-    text: String, // The value as it appears in the source
-    line: usize,
+// Tokens and errors merged in source order; the sort is stable, so an error sharing a
+// position with a token (e.g. EOF after an unterminated string) comes first
+fn interleaved_listing(tokens: &[Token], errors: &[Error], origin: &str) -> Vec<String> {
+    let mut entries: Vec<(usize, usize, String)> = errors
+        .iter()
+        .map(|e| (e.line, e.column, format!("{}[line {}:{}] Error: {}", origin, e.line, e.column, e.error_type)))
+        .chain(tokens.iter().map(|t| (t.line, t.column, t.to_string())))
+        .collect();
+    entries.sort_by_key(|(line, column, _)| (*line, *column));
+    entries.into_iter().map(|(_, _, entry)| entry).collect()
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum TokenType {
-    LEFT_PAREN,
-    RIGHT_PAREN,
-    LEFT_BRACE,
-    RIGHT_BRACE,
-    STAR,
-    DOT,
-    COMMA,
-    PLUS,
-    MINUS,
-    SEMICOLON,
-    SLASH,
-    EQUAL,
-    EQUAL_EQUAL,
-    BANG,
-    BANG_EQUAL,
-    LESS,
-    LESS_EQUAL,
-    GREATER,
-    GREATER_EQUAL,
-    EOF,
-    LINE_BREAK,
-    ERROR,
-    STRING,
-    NUMBER,
-    IDENTIFIER,
-    WHITESPACE,
-    AND,
-    CLASS,
-    ELSE,
-    FALSE,
-    FOR,
-    FUN,
-    IF,
-    NIL,
-    OR,
-    PRINT,
-    RETURN,
-    SUPER,
-    THIS,
-    TRUE,
-    VAR,
-    WHILE,
+// One NDJSON object per token
+fn pipe_line(token: &Token) -> String {
+    format!("{{\"schema_version\":{},{}}}", JSON_SCHEMA_VERSION, token.json_fields())
 }
 
-static TOKENS: LazyLock<HashMap<TokenType, &'static str>> = LazyLock::new(|| {
-    HashMap::from([
-        (TokenType::LEFT_PAREN, "("),
-        (TokenType::RIGHT_PAREN, ")"),
-        (TokenType::LEFT_BRACE, "{"),
-        (TokenType::RIGHT_BRACE, "}"),
-        (TokenType::STAR, "*"),
-        (TokenType::DOT, "."),
-        (TokenType::COMMA, ","),
-        (TokenType::PLUS, "+"),
-        (TokenType::MINUS, "-"),
-        (TokenType::SEMICOLON, ";"),
-        (TokenType::SLASH, "/"),
-        (TokenType::EQUAL, "="),
-        (TokenType::EQUAL_EQUAL, "=="),
-        (TokenType::BANG, "!"),
-        (TokenType::BANG_EQUAL, "!="),
-        (TokenType::LESS, "<"),
-        (TokenType::LESS_EQUAL, "<="),
-        (TokenType::GREATER, ">"),
-        (TokenType::GREATER_EQUAL, ">="),
-        (TokenType::LINE_BREAK, ""),
-        (TokenType::EOF, ""),
-        (TokenType::AND, "and"),
-        (TokenType::CLASS, "class"),
-        (TokenType::ELSE, "else"),
-        (TokenType::FALSE, "false"),
-        (TokenType::FOR, "for"),
-        (TokenType::FUN, "fun"),
-        (TokenType::IF, "if"),
-        (TokenType::NIL, "nil"),
-        (TokenType::OR, "or"),
-        (TokenType::PRINT, "print"),
-        (TokenType::RETURN, "return"),
-        (TokenType::SUPER, "super"),
-        (TokenType::THIS, "this"),
-        (TokenType::TRUE, "true"),
-        (TokenType::VAR, "var"),
-        (TokenType::WHILE, "while"),
-    ])
-});
-
-impl TokenType {
-    fn parse(c: &str) -> Option<TokenType> {
-        // First try and match simple patterns
-        let tt = match c {
-            "(" => Some(TokenType::LEFT_PAREN),
-            ")" => Some(TokenType::RIGHT_PAREN),
-            "{" => Some(TokenType::LEFT_BRACE),
-            "}" => Some(TokenType::RIGHT_BRACE),
-            "*" => Some(TokenType::STAR),
-            "." => Some(TokenType::DOT),
-            "," => Some(TokenType::COMMA),
-            "+" => Some(TokenType::PLUS),
-            "-" => Some(TokenType::MINUS),
-            ";" => Some(TokenType::SEMICOLON),
-            "/" => Some(TokenType::SLASH),
-            "=" => Some(TokenType::EQUAL),
-            "==" => Some(TokenType::EQUAL_EQUAL),
-            "!" => Some(TokenType::BANG),
-            "!=" => Some(TokenType::BANG_EQUAL),
-            "<" => Some(TokenType::LESS),
-            "<=" => Some(TokenType::LESS_EQUAL),
-            ">" => Some(TokenType::GREATER),
-            ">=" => Some(TokenType::GREATER_EQUAL),
-            "\r" | "\t" | " " => Some(TokenType::WHITESPACE),
-            "\n" => Some(TokenType::LINE_BREAK),
-            "\"" => Some(TokenType::STRING),
-            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => Some(TokenType::NUMBER),
-            "" => Some(TokenType::EOF),
-            _ => None,
-        };
-        if tt.is_some() { return tt };
+// One document with parallel token and error arrays
+fn json_listing(tokens: &[Token], errors: &[Error]) -> String {
+    let tokens: Vec<String> = tokens.iter().map(|t| format!("{{{}}}", t.json_fields())).collect();
+    let errors: Vec<String> = errors.iter().map(|e| format!("{{{}}}", e.json_fields())).collect();
+    format!(
+        "{{\"schema_version\":{},\"tokens\":[{}],\"errors\":[{}]}}",
+        JSON_SCHEMA_VERSION,
+        tokens.join(","),
+        errors.join(",")
+    )
+}
 
-        // Try matching complex expressions if simple ones faile
-        if Scanner::is_beginning_identifier_char(c) {
-            return Some(TokenType::IDENTIFIER);
-        }
+// Reports a mistake on the command line and exits with EX_USAGE
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(64);
+}
 
-        // Return an error if nothing matches
-        Some(TokenType::ERROR)
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 1 || args[1] == "repl" {
+        repl();
+        return;
     }
-
-    fn parse_keyword(token: &str) -> Option<TokenType> {
-        match token {
-            "and" => Some(TokenType::AND),
-            "class" => Some(TokenType::CLASS),
-            "else" => Some(TokenType::ELSE),
-            "false" => Some(TokenType::FALSE),
-            "for" => Some(TokenType::FOR),
-            "fun" => Some(TokenType::FUN),
-            "if" => Some(TokenType::IF),
-            "nil" => Some(TokenType::NIL),
-            "or" => Some(TokenType::OR),
-            "print" => Some(TokenType::PRINT),
-            "return" => Some(TokenType::RETURN),
-            "super" => Some(TokenType::SUPER),
-            "this" => Some(TokenType::THIS),
-            "true" => Some(TokenType::TRUE),
-            "var" => Some(TokenType::VAR),
-            "while" => Some(TokenType::WHILE),
-            _ => None
-        }
+    if args.len() < 3 {
+        usage_error(&format!("Usage: {} {}", args[0], USAGE));
     }
-}
 
-struct Scanner {
-    source: String,
-    tokens: Vec<Token>,
-    errors: Vec<Error>,
-    start: usize,
-    current: usize,
-    line: usize,
-    has_errors: bool
-}
-
-impl Scanner {
-    fn new(source: String) -> Scanner {
-        Scanner {
-            source,
-            tokens: Vec::new(),
-            errors: Vec::new(),
-            start: 0,
-            current: 0,
-            line: 1,
-            has_errors: false,
+    let command = &args[1];
+    let mut listing_flag: Option<&String> = None;
+    let mut check_delimiters = false;
+    let mut keep_whitespace = false;
+    let mut stats = false;
+    let mut segmentation = Segmentation::Grapheme;
+    let mut start_line = 1;
+    let mut max_line_length = None;
+    let mut max_errors = None;
+    let mut tab_width = None;
+    let mut defines = Vec::new();
+    let mut filename = None;
+    let mut inline_source = None;
+    let mut flags = args[2..].iter();
+    while let Some(arg) = flags.next() {
+        match arg.as_str() {
+            "--only-errors" | "--emit-lines" | "--pipe" | "--json" | "--omit-literals" | "--doc-comments" | "--count"
+            | "--interleave" => {
+                // The listings are alternatives, so asking for two is a mistake rather than "last one wins"
+                if let Some(previous) = listing_flag.filter(|&previous| previous != arg) {
+                    usage_error(&format!("{} can't be combined with {}", arg, previous));
+                }
+                listing_flag = Some(arg);
+            }
+            "--check-delimiters" => check_delimiters = true,
+            "--keep-whitespace" => keep_whitespace = true,
+            "--stats" => stats = true,
+            "--segmentation" => {
+                let Some(mode) = flags.next().and_then(|m| Segmentation::parse(m)) else {
+                    usage_error("--segmentation expects 'grapheme' or 'scalar'");
+                };
+                segmentation = mode;
+            }
+            "--start-line" => {
+                let Some(line) = flags.next().and_then(|n| n.parse::<usize>().ok()).filter(|&n| n > 0) else {
+                    usage_error("--start-line expects a line number, counting from 1");
+                };
+                start_line = line;
+            }
+            "--max-line-length" => {
+                let Some(max) = flags.next().and_then(|n| n.parse::<usize>().ok()) else {
+                    usage_error("--max-line-length expects a number of characters");
+                };
+                max_line_length = Some(max);
+            }
+            "--max-errors" => {
+                let Some(max) = flags.next().and_then(|n| n.parse::<usize>().ok()).filter(|&n| n > 0) else {
+                    usage_error("--max-errors expects a positive number of errors");
+                };
+                max_errors = Some(max);
+            }
+            "--tab-width" => {
+                let Some(width) = flags.next().and_then(|n| n.parse::<usize>().ok()) else {
+                    usage_error("--tab-width expects a number of columns");
+                };
+                tab_width = Some(width);
+            }
+            "--define" => {
+                let Some(flag) = flags.next() else {
+                    usage_error("--define expects a flag name");
+                };
+                defines.push(flag.clone());
+            }
+            "-c" | "-e" => {
+                let Some(code) = flags.next() else {
+                    usage_error(&format!("{} expects the program source", arg));
+                };
+                inline_source = Some(code.clone());
+            }
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown flag: {}", arg)),
+            _ if filename.is_some() => {
+                usage_error(&format!("Unexpected argument: {} (only one source file is allowed)", arg))
+            }
+            _ => filename = Some(arg),
         }
     }
+    if inline_source.is_none() && filename.is_none() {
+        usage_error(&format!("Usage: {} {}", args[0], USAGE));
+    }
+    if !matches!(command.as_str(), "tokenize" | "parse" | "evaluate" | "run") {
+        usage_error(&format!("Unknown command: {}", command));
+    }
+    let listing = match listing_flag.map(String::as_str) {
+        None => TokenListing::Full,
+        Some("--only-errors") => TokenListing::None,
+        Some("--emit-lines") => TokenListing::Lines,
+        Some("--pipe") => TokenListing::Pipe,
+        Some("--json") => TokenListing::Json,
+        Some("--omit-literals") => TokenListing::OmitLiterals,
+        Some("--doc-comments") => TokenListing::DocComments,
+        Some("--count") => TokenListing::Count,
+        Some("--interleave") => TokenListing::Interleaved,
+        Some(_) => unreachable!(),
+    };
+    // WHITESPACE tokens are for listing only; the parser must never see them
+    if keep_whitespace && command != "tokenize" {
+        usage_error("--keep-whitespace only applies to tokenize");
+    }
 
-    fn scan_tokens(&mut self) -> () {
-        while !self.eof() {
-            self.start = self.current;
-            let c = self.advance();
-            if let Some(lexeme) = TokenType::parse(c.to_string().as_str()) {
-                match lexeme {
-                    TokenType::BANG => {
-                        if self.is_compound_token('=') {
-                            self.add_token(TokenType::BANG_EQUAL, None);
-                        } else {
-                            self.add_token(lexeme, None);
-                        }
-                    }
-                    TokenType::EQUAL => {
-                        if self.is_compound_token('=') {
-                            self.add_token(TokenType::EQUAL_EQUAL, None);
-                        } else {
-                            self.add_token(lexeme, None);
-                        }
-                    }
-                    TokenType::GREATER => {
-                        if self.is_compound_token('=') {
-                            self.add_token(TokenType::GREATER_EQUAL, None);
-                        } else {
-                            self.add_token(lexeme, None);
-                        }
-                    }
-                    TokenType::LESS => {
-                        if self.is_compound_token('=') {
-                            self.add_token(TokenType::LESS_EQUAL, None);
-                        } else {
-                            self.add_token(lexeme, None);
-                        }
-                    }
-                    TokenType::LINE_BREAK => {
-                        self.line += 1;
-                    }
-                    TokenType::SLASH => {
-                        if self.is_compound_token('/') {
-                            while !self.eof() && self.peek() != "\n" {
-                                self.advance(); // Ignore comments
-                            }
-                        } else {
-                            self.add_token(lexeme, None);
-                        }
-                    }
-                    TokenType::STRING => {
-                        while !self.eof() && self.peek() != "\"" {
-                            self.advance();
-                        }
-                        if !self.eof() {
-                            self.advance();
-                            self.add_token(lexeme, Some(Literal::String(self.substr(self.start + 1, self.current - 1))));
-                        } else {
-                            self.add_error(ErrorType::UnterminatedString(self.substr(self.start, self.current)));
-                        }
-                    }
-                    TokenType::NUMBER => {
-                        while !self.eof() {
-                            if self.peek().chars().nth(0).unwrap() == '.' {
-                                self.advance();
-                                while !self.eof() && self.peek().chars().nth(0).unwrap().is_digit(10) {
-                                    self.advance();
-                                }
-                                break;
-                            }
-                            if !self.peek().chars().nth(0).unwrap().is_digit(10) {
-                                break;
-                            }
-                            self.advance();
-                        }
-                        self.add_token(lexeme, Some(Literal::Number(self.substr(self.start, self.current).parse::<f64>().unwrap())));
-                    }
-                    TokenType::WHITESPACE => { } // ignore
-                    TokenType::IDENTIFIER => {
-                        while !self.eof() && Scanner::is_identifier_char(self.peek()) {
-                            self.advance();
-                        }
-                        let word = self.substr(self.start, self.current).to_string();
-                        if let Some(keyword) = TokenType::parse_keyword(word.as_str()) {
-                            self.add_token(keyword, Some(Literal::Keyword(word)));
-                        } else {
-                            self.add_token(TokenType::IDENTIFIER, Some(Literal::Identifier(self.substr(self.start, self.current).to_string())));
-                        }
-                    }
-                    TokenType::ERROR => {
-                        let unexpected_char = self.source.chars().nth(self.start).unwrap().to_string();
-                        self.add_error(ErrorType::UnexpectedCharacter(unexpected_char));
-                    }
-                    _ => self.add_token(lexeme, None)
+    // Inline programs have no file name, so their diagnostics name the command line instead
+    let origin = if inline_source.is_some() { "<command-line>: " } else { "" };
+    let source = match inline_source {
+        Some(code) => code,
+        None => {
+            let filename = filename.unwrap();
+            // `-` reads the program from stdin, for pipelines
+            let read = if filename == "-" {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source).map(|_| source)
+            } else {
+                fs::read_to_string(filename)
+            };
+            match read {
+                Ok(source) => source,
+                Err(_) => {
+                    eprintln!("Error: could not read file {}", filename);
+                    exit(66); // EX_NOINPUT
                 }
             }
         }
-        self.add_token(TokenType::EOF, None);
+    };
+    let source = match preprocess(&source, &defines) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}[line {}:{}] Error: {}", origin, e.line + start_line - 1, e.column, e.error_type);
+            exit(65);
+        }
+    };
+    let mut scanner = Scanner::new(source)
+        .with_segmentation(segmentation)
+        .with_start_line(start_line);
+    if let Some(max) = max_line_length {
+        scanner = scanner.with_max_line_length(max);
     }
-
-    fn is_beginning_identifier_char(c: &str) -> bool {
-        (c >= "a" && c <= "z") || (c >= "A" && c <="Z") || c == "_"
+    if let Some(width) = tab_width {
+        scanner = scanner.with_tab_width(width);
     }
-
-    fn is_identifier_char(c: &str) -> bool {
-        c.chars().nth(0).unwrap().is_alphanumeric() || Scanner::is_beginning_identifier_char(c)
+    if let Some(max) = max_errors {
+        scanner = scanner.with_max_errors(max);
     }
-
-    fn substr(&self, start: usize, end: usize) -> String {
-        self.source.graphemes(true).skip(start).take(end - start).collect()
+    if matches!(listing, TokenListing::DocComments) {
+        scanner = scanner.with_doc_comments();
     }
-    fn advance(&mut self) -> &str {
-        self.current += 1;
-        self.source.graphemes(true).nth(self.current-1).unwrap()
+    if keep_whitespace {
+        scanner = scanner.with_whitespace_tokens();
     }
-
-    fn peek(&self) -> &str {
-        self.source.graphemes(true).nth(self.current).unwrap()
+    // Drives the scanner by hand rather than through scan_tokens so --pipe can write
+    // each token, flushed, the moment it is scanned
+    let streaming = command == "tokenize" && matches!(listing, TokenListing::Pipe);
+    // --interleave lists errors on stdout alongside the tokens instead of on stderr
+    let interleaved = command == "tokenize" && matches!(listing, TokenListing::Interleaved);
+    {
+        let mut stdout = io::stdout().lock();
+        while let Some(item) = scanner.next() {
+            match item {
+                Ok(token) => {
+                    if streaming {
+                        writeln!(stdout, "{}", pipe_line(&token)).unwrap();
+                        stdout.flush().unwrap();
+                    }
+                    scanner.tokens.push(token);
+                }
+                Err(e) => {
+                    if !interleaved {
+                        eprintln!("{}[line {}:{}] Error: {}", origin, e.line, e.column, e.error_type);
+                    }
+                    scanner.errors.push(e);
+                }
+            }
+        }
     }
 
-    fn add_error(&mut self, error_type: ErrorType) -> () {
-        self.has_errors = true;
-        let error = Error {
-            error_type,
-            line: self.line
-        };
-        self.errors.push(error);
+    scanner.warnings.iter().for_each(|w| eprintln!("{}[line {}:{}] Warning: {}", origin, w.line, w.column, w.error_type));
+    if scanner.truncated {
+        eprintln!("Too many errors; stopped scanning after {}.", scanner.errors.len());
+    }
+    // Only tokenize goes on to list tokens after a failed scan; it exits 65 once it has
+    if scanner.has_errors && command != "tokenize" {
+        exit(65);
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) -> () {
-        let token = if token_type == TokenType::EOF {
-            Token {
-                token_type: token_type,
-                text: String::from(""),
-                literal,
-                line: 1,
+    match command.as_str() {
+        "tokenize" => {
+            // You can use print statements as follows for debugging, they'll be visible when running tests.
+            eprintln!("Logs from your program will appear here!");
+
+            let mut has_errors = scanner.has_errors;
+            if check_delimiters {
+                for (token_type, line, column) in scanner.unmatched_delimiters() {
+                    eprintln!("{}[line {}:{}] Error: Unmatched '{}'.", origin, line, column, TOKENS[&token_type]);
+                    has_errors = true;
+                }
             }
-        } else {
-            let text = self.source.graphemes(true).skip(self.start).take(self.current - self.start).collect();
-            Token {
-                token_type: token_type,
-                text: text,
-                literal: literal,
-                line: 1,
+            let doc_comments = std::mem::take(&mut scanner.doc_comments);
+            let (tokens, errors) = scanner.into_parts();
+            match listing {
+                TokenListing::Full => {
+                    tokens.iter().for_each(|token| println!("{}", token));
+                }
+                TokenListing::OmitLiterals => {
+                    omit_literals_listing(&tokens).iter().for_each(|line| println!("{}", line));
+                }
+                TokenListing::Lines => {
+                    line_listing(&tokens).iter().for_each(|line| println!("{}", line));
+                }
+                TokenListing::Pipe => {
+                    // Already written, one JSON object per line, while scanning
+                }
+                TokenListing::Json => {
+                    println!("{}", json_listing(&tokens, &errors));
+                }
+                TokenListing::DocComments => {
+                    for ((start, end), doc) in doc_comments {
+                        println!("{}..{} {}", start, end, doc);
+                    }
+                }
+                TokenListing::Count => {
+                    count_listing(&tokens).iter().for_each(|line| println!("{}", line));
+                }
+                TokenListing::Interleaved => {
+                    interleaved_listing(&tokens, &errors, origin).iter().for_each(|line| println!("{}", line));
+                }
+                TokenListing::None => {}
+            }
+            if stats {
+                eprintln!("scanned {} tokens, {} errors", tokens.len(), errors.len());
             }
-        };
-        self.tokens.push(token);
-    }
 
-    fn is_compound_token(&mut self, c: char) -> bool {
-        if self.eof() {
-            return false;
+            if has_errors {
+               exit(65);
+            }
+        }
+        "parse" => {
+            let mut parser = Parser::new(scanner.into_tokens());
+            match parser.parse() {
+                Ok(expr) => println!("{}", AstPrinter.print(&expr)),
+                Err(e) => {
+                    eprintln!("{}{}", origin, e);
+                    exit(65);
+                }
+            }
+        }
+        "evaluate" => {
+            let mut parser = Parser::new(scanner.into_tokens());
+            let expr = match parser.parse() {
+                Ok(expr) => expr,
+                Err(e) => {
+                    eprintln!("{}{}", origin, e);
+                    exit(65);
+                }
+            };
+            match Interpreter::new().evaluate(&expr) {
+                Ok(value) => println!("{}", value),
+                Err(e) => {
+                    eprintln!("{}{}", origin, e);
+                    exit(70);
+                }
+            }
         }
-        if self.source.graphemes(true).nth(self.current).unwrap() == c.to_string().as_str() {
-            self.current += 1;
-            true
-        } else {
-            false
+        "run" => {
+            let mut parser = Parser::new(scanner.into_tokens());
+            let statements = match parser.parse_program() {
+                Ok(statements) => statements,
+                Err(e) => {
+                    eprintln!("{}{}", origin, e);
+                    exit(65);
+                }
+            };
+            if let Err(e) = Interpreter::new().interpret(&statements) {
+                eprintln!("{}{}", origin, e);
+                exit(70);
+            }
         }
+        _ => unreachable!(),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn eof(&self) -> bool {
-        self.current == self.source.chars().count()
+    fn tokens(source: &str) -> Vec<Token> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        scanner.into_tokens()
     }
-}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
-        return;
+    fn parse_json(json: &str) -> serde_json::Value {
+        serde_json::from_str(json).unwrap_or_else(|e| panic!("{}: {}", e, json))
     }
 
-    let command = &args[1];
-    let filename = &args[2];
+    #[test]
+    fn pipes_one_json_object_per_line() {
+        let tokens = tokens("var s = \"a \\\"q\\\"\";\nprint s + 1.5;");
+        assert_eq!(tokens.len(), 11);
+        for token in &tokens {
+            let line = pipe_line(token);
+            assert!(!line.contains('\n'));
+            assert!(parse_json(&line).is_object(), "{}", line);
+        }
+        assert_eq!(parse_json(&pipe_line(&tokens[3]))["literal"], "a \"q\"");
+    }
 
-    match command.as_str() {
-        "tokenize" => {
-            // You can use print statements as follows for debugging, they'll be visible when running tests.
-            writeln!(io::stderr(), "Logs from your program will appear here!").unwrap();
+    #[test]
+    fn stamps_json_output_with_the_schema_version() {
+        let version = format!("{{\"schema_version\":{},", JSON_SCHEMA_VERSION);
+        let mut scanner = Scanner::new(String::from("x @"));
+        scanner.scan_tokens();
+        let (tokens, errors) = scanner.into_parts();
 
-            let mut has_errors = false;
-            let mut scanner = Scanner::new(fs::read_to_string(filename).unwrap());
-            scanner.scan_tokens();
+        let json = json_listing(&tokens, &errors);
+        assert!(parse_json(&json).is_object(), "{}", json);
+        assert!(json.starts_with(&version));
+        assert!(json.contains("\"errors\":[{\"message\":\"Unexpected character: @\""));
+        assert!(tokens.iter().all(|token| pipe_line(token).starts_with(&version)));
+    }
 
-            scanner.errors.iter().for_each(|e| eprintln!("[line {}] Error: {}", e.line, e.error_type));
-            scanner.tokens.iter().for_each(|l| {
-                println!("{:?} {} {}", l.token_type, l.text.as_str(), l.literal.clone().unwrap_or(Literal::NULL));;
-            });
+    #[test]
+    fn interleaves_errors_with_tokens_in_source_order() {
+        let mut scanner = Scanner::new(String::from("a @\n\"b"));
+        scanner.scan_tokens();
+        let (tokens, errors) = scanner.into_parts();
+        let listing = interleaved_listing(&tokens, &errors, "<command-line>: ");
+        assert_eq!(
+            listing,
+            vec![
+                "IDENTIFIER a null",
+                "<command-line>: [line 1:3] Error: Unexpected character: @",
+                "<command-line>: [line 2:3] Error: Unterminated string.",
+                "EOF  null",
+            ]
+        );
+    }
 
-            if scanner.has_errors {
-               exit(65);
-            }
-        }
-        _ => {
-            writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-            return;
-        }
+    #[test]
+    fn omits_literals() {
+        let listing = omit_literals_listing(&tokens("\"hi\" 2"));
+        assert_eq!(listing, vec!["STRING \"hi\"", "NUMBER 2", "EOF "]);
+    }
+
+    #[test]
+    fn orders_counts_by_frequency_then_name() {
+        let listing = count_listing(&tokens("x + y - z * 1 + 2 - 3"));
+        assert_eq!(listing, vec![
+            "IDENTIFIER 3",
+            "NUMBER 3",
+            "MINUS 2",
+            "PLUS 2",
+            "EOF 1",
+            "STAR 1",
+        ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn lists_token_types_per_line() {
+        let listing = line_listing(&tokens("var x = 1;\n\n// nothing here\nprint x;"));
+        assert_eq!(listing, vec![
+            "line 1: VAR IDENTIFIER EQUAL NUMBER SEMICOLON",
+            "line 4: PRINT IDENTIFIER SEMICOLON",
+        ]);
+    }
+}
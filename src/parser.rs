@@ -0,0 +1,238 @@
+use std::fmt::{Display, Formatter};
+use crate::{Literal, Token, TokenType};
+use crate::expr::Expr;
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    token: Token,
+    message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.token.token_type == TokenType::EOF {
+            write!(f, "[line {}] Error at end: {}", self.token.line, self.message)
+        } else {
+            write!(f, "[line {}] Error at '{}': {}", self.token.line, self.token.text, self.message)
+        }
+    }
+}
+
+// Recursive descent over the scanned tokens, one method per precedence level, lowest first
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        Parser {
+            tokens,
+            current: 0,
+        }
+    }
+
+    // A single expression, for the parse and evaluate commands. Anything after it is an error.
+    pub fn parse(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(Parser::error(self.peek(), "Expected end of input."));
+        }
+        Ok(expr)
+    }
+
+    // A whole program: declarations up to EOF
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+        Ok(statements)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::VAR]) {
+            return self.var_declaration();
+        }
+        self.statement()
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, "Expect variable name.")?.clone();
+        let initializer = if self.match_any(&[TokenType::EQUAL]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::SEMICOLON, "Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var(name, initializer))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::PRINT]) {
+            let value = self.expression()?;
+            self.consume(TokenType::SEMICOLON, "Expect ';' after value.")?;
+            return Ok(Stmt::Print(value));
+        }
+        let expr = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.equality()
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL], Parser::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[TokenType::GREATER, TokenType::GREATER_EQUAL, TokenType::LESS, TokenType::LESS_EQUAL],
+            Parser::term,
+        )
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[TokenType::MINUS, TokenType::PLUS], Parser::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[TokenType::SLASH, TokenType::STAR], Parser::unary)
+    }
+
+    // A left-associative run of `operand (operator operand)*`
+    fn binary(
+        &mut self,
+        operators: &[TokenType],
+        operand: fn(&mut Parser) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let mut expr = operand(self)?;
+        while self.match_any(operators) {
+            let operator = self.previous().clone();
+            let right = operand(self)?;
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_any(&[TokenType::BANG, TokenType::MINUS]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Unary(operator, Box::new(right)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_any(&[TokenType::LEFT_PAREN]) {
+            let expr = self.expression()?;
+            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping(Box::new(expr)));
+        }
+        if self.match_any(&[TokenType::IDENTIFIER]) {
+            return Ok(Expr::Variable(self.previous().clone()));
+        }
+        let value = match (&self.peek().token_type, &self.peek().literal) {
+            (TokenType::FALSE | TokenType::TRUE, Some(Literal::Boolean(b))) => Value::Boolean(*b),
+            (TokenType::NIL, Some(Literal::Nil)) => Value::Nil,
+            (TokenType::NUMBER, Some(Literal::Number(n))) => Value::Number(*n),
+            (TokenType::STRING, Some(Literal::String(s))) => Value::String(s.clone()),
+            _ => return Err(Parser::error(self.peek(), "Expect expression.")),
+        };
+        self.advance();
+        Ok(Expr::Literal(value))
+    }
+
+    fn match_any(&mut self, token_types: &[TokenType]) -> bool {
+        if token_types.iter().any(|t| self.check(t)) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, ParseError> {
+        if self.check(&token_type) {
+            return Ok(self.advance());
+        }
+        Err(Parser::error(self.peek(), message))
+    }
+
+    fn check(&self, token_type: &TokenType) -> bool {
+        !self.is_at_end() && self.peek().token_type == *token_type
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::EOF
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    fn error(token: &Token, message: &str) -> ParseError {
+        ParseError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_printer::AstPrinter;
+    use crate::Scanner;
+
+    fn parse(source: &str) -> Result<String, String> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        match Parser::new(scanner.into_tokens()).parse() {
+            Ok(expr) => Ok(AstPrinter.print(&expr)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[test]
+    fn follows_precedence_and_associativity() {
+        assert_eq!(parse("1 + 2 * 3 - 4").unwrap(), "(- (+ 1.0 (* 2.0 3.0)) 4.0)");
+        assert_eq!(parse("8 / 4 / 2").unwrap(), "(/ (/ 8.0 4.0) 2.0)");
+        assert_eq!(parse("1 < 2 == !false").unwrap(), "(== (< 1.0 2.0) (! false))");
+        assert_eq!(parse("--1").unwrap(), "(- (- 1.0))");
+    }
+
+    #[test]
+    fn parses_groupings() {
+        assert_eq!(parse("(1 + 2) * 3").unwrap(), "(* (group (+ 1.0 2.0)) 3.0)");
+        assert_eq!(parse("((\"a\"))").unwrap(), "(group (group a))");
+    }
+
+    #[test]
+    fn reports_the_offending_token() {
+        assert_eq!(parse("(1 + 2").unwrap_err(), "[line 1] Error at end: Expect ')' after expression.");
+        assert_eq!(parse("1 +\n)").unwrap_err(), "[line 2] Error at ')': Expect expression.");
+        assert_eq!(parse("").unwrap_err(), "[line 1] Error at end: Expect expression.");
+    }
+
+    #[test]
+    fn rejects_trailing_tokens() {
+        assert_eq!(parse("1 2 )").unwrap_err(), "[line 1] Error at '2': Expected end of input.");
+        assert_eq!(parse("(3 + 4) * 2 foo").unwrap_err(), "[line 1] Error at 'foo': Expected end of input.");
+    }
+}
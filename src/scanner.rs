@@ -0,0 +1,1370 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Number(f64),
+    Identifier(String),
+    Keyword(String),
+    Boolean(bool),
+    Nil,
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "{}", s),
+            Literal::Number(n) => write!(f, "{:?}", n),
+            Literal::Boolean(b) => write!(f, "{}", b),
+            Literal::Nil => write!(f, "nil"),
+            Literal::Identifier(_) | Literal::Keyword(_) => write!(f, "null"),
+        }
+    }
+}
+
+impl Literal {
+    fn to_json(&self) -> String {
+        match self {
+            Literal::String(s) => json_string(s),
+            // JSON has no infinity, which an overflowing literal like 1e999 scans to
+            Literal::Number(n) if !n.is_finite() => String::from("null"),
+            Literal::Number(n) => format!("{:?}", n),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::Identifier(_) | Literal::Keyword(_) | Literal::Nil => String::from("null"),
+        }
+    }
+}
+
+// Top-level "schema_version" of every JSON output; bump only on breaking format changes
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[derive(Debug, Clone)]
+pub enum ErrorType {
+    UnexpectedCharacter(String),
+    UnterminatedString(String),
+    LineTooLong(usize),
+    NestedDirective,
+    UnterminatedDirective,
+    UnmatchedEndif,
+    UnterminatedComment,
+    InvalidEscape(String),
+}
+
+impl Display for ErrorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorType::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
+            ErrorType::UnterminatedString(_) => write!(f, "Unterminated string."),
+            ErrorType::LineTooLong(max) => write!(f, "Line exceeds {} characters.", max),
+            ErrorType::NestedDirective => write!(f, "Nested '#if' blocks are not supported."),
+            ErrorType::UnterminatedDirective => write!(f, "Unterminated '#if' block."),
+            ErrorType::UnmatchedEndif => write!(f, "'#endif' without a matching '#if'."),
+            ErrorType::UnterminatedComment => write!(f, "Unterminated block comment."),
+            ErrorType::InvalidEscape(sequence) => write!(f, "Invalid escape sequence: {}", sequence),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub error_type: ErrorType,
+    pub line: usize,
+    pub column: usize, // 1-based, counted in characters from the start of the line
+}
+
+impl Error {
+    // The error's JSON members, without the surrounding braces
+    pub fn json_fields(&self) -> String {
+        format!(
+            "\"message\":{},\"line\":{},\"column\":{}",
+            json_string(&self.error_type.to_string()),
+            self.line,
+            self.column
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub token_type: TokenType,
+    pub literal: Option<Literal>, // The value as represented internally
+    pub text: String, // The value as it appears in the source
+    pub line: usize,
+    pub column: usize,
+    pub span: Span, // Where `text` sits in the source, so `&source[span.0..span.1] == text`
+}
+
+// The CodeCrafters token format: `<TOKEN_TYPE> <lexeme> <literal-or-null>`. Only strings and
+// numbers show their literal there; `true`, `false` and `nil` print null like other keywords.
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.literal {
+            Some(literal @ (Literal::String(_) | Literal::Number(_))) => write!(f, "{:?} {} {}", self.token_type, self.text, literal),
+            _ => write!(f, "{:?} {} null", self.token_type, self.text),
+        }
+    }
+}
+
+impl Token {
+    // The token's JSON members, without the surrounding braces so callers can add their own
+    pub fn json_fields(&self) -> String {
+        format!(
+            "\"type\":\"{:?}\",\"lexeme\":{},\"literal\":{},\"line\":{}",
+            self.token_type,
+            json_string(&self.text),
+            self.literal.as_ref().map_or(String::from("null"), Literal::to_json),
+            self.line
+        )
+    }
+
+    // Compares token_type and literal only; text, line, column and span are ignored. `None`
+    // matches only tokens without a literal, such as operators and EOF.
+    pub fn matches(&self, token_type: &TokenType, literal: Option<&Literal>) -> bool {
+        self.token_type == *token_type && self.literal.as_ref() == literal
+    }
+
+    // Compares type, text and literal only, so golden tokens survive edits that shift positions
+    pub fn eq_ignoring_position(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && self.text == other.text && self.literal == other.literal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum TokenType {
+    LEFT_PAREN,
+    RIGHT_PAREN,
+    LEFT_BRACE,
+    RIGHT_BRACE,
+    STAR,
+    DOT,
+    COMMA,
+    PLUS,
+    MINUS,
+    SEMICOLON,
+    SLASH,
+    EQUAL,
+    EQUAL_EQUAL,
+    BANG,
+    BANG_EQUAL,
+    LESS,
+    LESS_EQUAL,
+    GREATER,
+    GREATER_EQUAL,
+    EOF,
+    LINE_BREAK,
+    ERROR,
+    STRING,
+    NUMBER,
+    IDENTIFIER,
+    WHITESPACE,
+    AND,
+    CLASS,
+    ELSE,
+    FALSE,
+    FOR,
+    FUN,
+    IF,
+    NIL,
+    OR,
+    PRINT,
+    RETURN,
+    SUPER,
+    THIS,
+    TRUE,
+    VAR,
+    WHILE,
+}
+
+// The one table of tokens with a fixed lexeme. TOKENS and the reverse lookup used by
+// `TokenType::parse` and keyword scanning are both built from it; tokens without a fixed
+// lexeme (strings, numbers, identifiers, whitespace) are classified in `TokenType::parse`.
+static LEXEMES: &[(TokenType, &str)] = &[
+    (TokenType::LEFT_PAREN, "("),
+    (TokenType::RIGHT_PAREN, ")"),
+    (TokenType::LEFT_BRACE, "{"),
+    (TokenType::RIGHT_BRACE, "}"),
+    (TokenType::STAR, "*"),
+    (TokenType::DOT, "."),
+    (TokenType::COMMA, ","),
+    (TokenType::PLUS, "+"),
+    (TokenType::MINUS, "-"),
+    (TokenType::SEMICOLON, ";"),
+    (TokenType::SLASH, "/"),
+    (TokenType::EQUAL, "="),
+    (TokenType::EQUAL_EQUAL, "=="),
+    (TokenType::BANG, "!"),
+    (TokenType::BANG_EQUAL, "!="),
+    (TokenType::LESS, "<"),
+    (TokenType::LESS_EQUAL, "<="),
+    (TokenType::GREATER, ">"),
+    (TokenType::GREATER_EQUAL, ">="),
+    (TokenType::EOF, ""),
+    (TokenType::AND, "and"),
+    (TokenType::CLASS, "class"),
+    (TokenType::ELSE, "else"),
+    (TokenType::FALSE, "false"),
+    (TokenType::FOR, "for"),
+    (TokenType::FUN, "fun"),
+    (TokenType::IF, "if"),
+    (TokenType::NIL, "nil"),
+    (TokenType::OR, "or"),
+    (TokenType::PRINT, "print"),
+    (TokenType::RETURN, "return"),
+    (TokenType::SUPER, "super"),
+    (TokenType::THIS, "this"),
+    (TokenType::TRUE, "true"),
+    (TokenType::VAR, "var"),
+    (TokenType::WHILE, "while"),
+];
+
+pub static TOKENS: LazyLock<HashMap<TokenType, &'static str>> =
+    LazyLock::new(|| LEXEMES.iter().cloned().collect());
+
+// Lexeme to token type; any other identifier-shaped lexeme is an IDENTIFIER
+static TOKEN_TYPES: LazyLock<HashMap<&'static str, TokenType>> =
+    LazyLock::new(|| LEXEMES.iter().map(|(token_type, lexeme)| (*lexeme, token_type.clone())).collect());
+
+// Two-character operators from LEXEMES, keyed by the type of their first character, e.g.
+// LESS -> [("=", LESS_EQUAL)]
+static COMPOUND_OPERATORS: LazyLock<HashMap<TokenType, Vec<(&'static str, TokenType)>>> = LazyLock::new(|| {
+    let mut compounds: HashMap<TokenType, Vec<(&'static str, TokenType)>> = HashMap::new();
+    for (token_type, lexeme) in LEXEMES {
+        let mut chars = lexeme.char_indices();
+        if let (Some((_, first)), Some((split, _)), None) = (chars.next(), chars.next(), chars.next()) {
+            if !first.is_alphabetic() {
+                let prefix = TOKEN_TYPES[&lexeme[..split]].clone();
+                compounds.entry(prefix).or_default().push((&lexeme[split..], token_type.clone()));
+            }
+        }
+    }
+    compounds
+});
+
+impl TokenType {
+    fn parse(c: &str) -> Option<TokenType> {
+        if let Some(token_type) = TOKEN_TYPES.get(c) {
+            return Some(token_type.clone());
+        }
+        let tt = match c {
+            "\t" | " " | "\u{000B}" | "\u{000C}" => Some(TokenType::WHITESPACE), // incl. vertical tab, form feed
+            "\n" | "\r\n" | "\r" => Some(TokenType::LINE_BREAK),
+            "\"" => Some(TokenType::STRING),
+            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => Some(TokenType::NUMBER),
+            _ => None,
+        };
+        if tt.is_some() { return tt };
+
+        // Try matching complex expressions if simple ones faile
+        if Scanner::is_beginning_identifier_char(c) {
+            return Some(TokenType::IDENTIFIER);
+        }
+
+        // Return an error if nothing matches
+        Some(TokenType::ERROR)
+    }
+}
+
+// Keeps or strips `//#if FLAG` ... `//#endif` blocks depending on which flags are defined.
+// Stripped lines are blanked rather than removed so line numbers still match the original file.
+pub fn preprocess(source: &str, defines: &[String]) -> Result<String, Error> {
+    let mut output = String::with_capacity(source.len());
+    let mut open_block: Option<(usize, bool)> = None; // (line, keep)
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let directive = line.trim();
+        if let Some(flag) = directive.strip_prefix("//#if ") {
+            if open_block.is_some() {
+                return Err(Error { error_type: ErrorType::NestedDirective, line: index + 1, column: 1 });
+            }
+            open_block = Some((index + 1, defines.iter().any(|d| d == flag.trim())));
+        } else if directive == "//#endif" {
+            if open_block.take().is_none() {
+                return Err(Error { error_type: ErrorType::UnmatchedEndif, line: index + 1, column: 1 });
+            }
+        } else if let Some((_, false)) = open_block {
+            output.push_str(&line[line.trim_end_matches(['\r', '\n']).len()..]);
+            continue;
+        }
+        output.push_str(line);
+    }
+    match open_block {
+        Some((line, _)) => Err(Error { error_type: ErrorType::UnterminatedDirective, line, column: 1 }),
+        None => Ok(output),
+    }
+}
+
+// Byte offsets [start, end) into the source
+pub type Span = (usize, usize);
+
+// How the source is split into the characters the scanner steps over. Graphemes match what an
+// editor shows; scalars match jlox, which walks Java chars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segmentation {
+    Grapheme,
+    Scalar,
+}
+
+impl Segmentation {
+    pub fn parse(mode: &str) -> Option<Segmentation> {
+        match mode {
+            "grapheme" => Some(Segmentation::Grapheme),
+            "scalar" => Some(Segmentation::Scalar),
+            _ => None
+        }
+    }
+
+    // Byte offset of each character in the source, plus the end of the source
+    fn boundaries(&self, source: &str) -> Vec<usize> {
+        let starts: Vec<usize> = match self {
+            Segmentation::Grapheme => source.grapheme_indices(true).map(|(i, _)| i).collect(),
+            Segmentation::Scalar => source.char_indices().map(|(i, _)| i).collect(),
+        };
+        starts.into_iter().chain(std::iter::once(source.len())).collect()
+    }
+}
+
+pub struct Scanner {
+    source: String,
+    boundaries: Vec<usize>, // Byte offset of each character, plus the end of the source
+    pub tokens: Vec<Token>,
+    pub errors: Vec<Error>,
+    pub warnings: Vec<Error>,
+    start: usize,
+    current: usize,
+    line: usize,
+    start_line: usize, // Line the current token started on, for tokens spanning several lines
+    start_column: usize,
+    line_start: usize, // Index of the first character on the current line
+    tab_width: usize,
+    column_cache: Cell<(usize, usize, usize)>, // (line_start, index, column) of the last column lookup
+    max_line_length: Option<usize>,
+    max_errors: Option<usize>,
+    collect_doc_comments: bool,
+    keep_whitespace: bool,
+    pub doc_comments: Vec<(Span, String)>,
+    pending_doc_comments: Vec<(Span, String)>, // Seen, but not yet followed by a declaration
+    pub has_errors: bool,
+    pub truncated: bool, // Scanning stopped early at the max_errors limit
+    error_count: usize,
+    pending: VecDeque<Result<Token, Error>>, // Scanned, but not yet handed out by the iterator
+    finished: bool, // EOF has been queued
+}
+
+impl Scanner {
+    pub fn new(source: String) -> Scanner {
+        let boundaries = Segmentation::Grapheme.boundaries(&source);
+        Scanner {
+            source,
+            boundaries,
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            start: 0,
+            current: 0,
+            line: 1,
+            start_line: 1,
+            start_column: 1,
+            line_start: 0,
+            tab_width: 8,
+            column_cache: Cell::new((0, 0, 1)),
+            max_line_length: None,
+            max_errors: None,
+            collect_doc_comments: false,
+            keep_whitespace: false,
+            doc_comments: Vec::new(),
+            pending_doc_comments: Vec::new(),
+            has_errors: false,
+            truncated: false,
+            error_count: 0,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    pub fn with_segmentation(mut self, segmentation: Segmentation) -> Scanner {
+        self.boundaries = segmentation.boundaries(&self.source);
+        self
+    }
+
+    // For snippets lifted out of a larger document, so reported lines stay absolute
+    pub fn with_start_line(mut self, line: usize) -> Scanner {
+        self.line = line;
+        self.start_line = line;
+        self
+    }
+
+    // Opt-in lint warning about any line longer than max characters
+    pub fn with_max_line_length(mut self, max: usize) -> Scanner {
+        self.max_line_length = Some(max);
+        self
+    }
+
+    // Columns count a tab as reaching the next multiple of width, like a monospaced editor
+    pub fn with_tab_width(mut self, width: usize) -> Scanner {
+        self.tab_width = width.max(1);
+        self
+    }
+
+    // Stop scanning once max errors have been reported, so garbage input can't flood the output.
+    // EOF is still emitted. A limit of 0 means no limit.
+    pub fn with_max_errors(mut self, max: usize) -> Scanner {
+        self.max_errors = (max > 0).then_some(max);
+        self
+    }
+
+    // Emit WHITESPACE tokens instead of skipping whitespace, for lossless source reconstruction
+    pub fn with_whitespace_tokens(mut self) -> Scanner {
+        self.keep_whitespace = true;
+        self
+    }
+
+    // Gather `///` comments that directly precede a fun, class or var declaration
+    pub fn with_doc_comments(mut self) -> Scanner {
+        self.collect_doc_comments = true;
+        self
+    }
+
+    // Scans everything up front into `tokens` and `errors`. Iterating the scanner instead yields
+    // the same items lazily, without collecting them.
+    pub fn scan_tokens(&mut self) {
+        while let Some(item) = self.next() {
+            match item {
+                Ok(token) => self.tokens.push(token),
+                Err(error) => self.errors.push(error),
+            }
+        }
+    }
+
+    // Scans one lexeme, queueing whatever tokens or errors it produces
+    fn scan_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column(self.start);
+        let c = self.advance();
+        if let Some(lexeme) = TokenType::parse(c.to_string().as_str()) {
+            match lexeme {
+                lexeme if COMPOUND_OPERATORS.contains_key(&lexeme) => {
+                    // Take the two-character operator when the rest of it follows, so `>=` wins over `>`
+                    let compound = COMPOUND_OPERATORS[&lexeme].iter().find(|(rest, _)| self.match_next(rest));
+                    match compound {
+                        Some((_, compound)) => self.add_token(compound.clone(), None),
+                        None => self.add_token(lexeme, None),
+                    }
+                }
+                TokenType::WHITESPACE | TokenType::LINE_BREAK if self.keep_whitespace => {
+                    // Emit the whole run of spaces, tabs and newlines as one token
+                    if self.ended_line() {
+                        self.new_line();
+                    }
+                    while matches!(TokenType::parse(self.peek()), Some(TokenType::WHITESPACE | TokenType::LINE_BREAK)) {
+                        self.advance();
+                        if self.ended_line() {
+                            self.new_line();
+                        }
+                    }
+                    self.add_token(TokenType::WHITESPACE, None);
+                }
+                TokenType::LINE_BREAK => {
+                    if self.ended_line() {
+                        self.new_line();
+                    }
+                }
+                TokenType::SLASH => {
+                    if self.match_next("/") {
+                        while !self.eof() && !Scanner::is_line_break(self.peek()) {
+                            self.advance(); // Ignore comments
+                        }
+                        if self.collect_doc_comments {
+                            // Like rustdoc, four or more slashes make an ordinary comment
+                            let doc = self.substr(self.start, self.current).strip_prefix("///").filter(|doc| !doc.starts_with('/'));
+                            if let Some(doc) = doc {
+                                let span = (self.boundaries[self.start], self.boundaries[self.current]);
+                                self.pending_doc_comments.push((span, doc.trim().to_string()));
+                            }
+                        }
+                    } else if self.match_next("*") {
+                        // Block comments nest, so track how many are still open
+                        let mut depth = 1;
+                        while !self.eof() && depth > 0 {
+                            if self.lookahead(2) == "/*" {
+                                self.current += 2;
+                                depth += 1;
+                            } else if self.lookahead(2) == "*/" {
+                                self.current += 2;
+                                depth -= 1;
+                            } else {
+                                self.advance();
+                                if self.ended_line() {
+                                    self.new_line();
+                                }
+                            }
+                        }
+                        if depth > 0 {
+                            self.add_error(ErrorType::UnterminatedComment);
+                        }
+                    } else {
+                        self.add_token(lexeme, None);
+                    }
+                }
+                TokenType::STRING if self.lookahead(2) == "\"\"" => {
+                    // Triple-quoted heredoc: verbatim until the closing quotes, newlines included
+                    self.current += 2;
+                    while !self.eof() && self.lookahead(3) != "\"\"\"" {
+                        self.advance();
+                        if self.ended_line() {
+                            self.new_line();
+                        }
+                    }
+                    if !self.eof() {
+                        self.current += 3;
+                        self.add_token(lexeme, Some(Literal::String(self.substr(self.start + 3, self.current - 3).to_string())));
+                    } else {
+                        self.add_error_at(
+                            ErrorType::UnterminatedString(self.substr(self.start, self.current).to_string()),
+                            self.line,
+                            self.column(self.current),
+                        );
+                    }
+                }
+                TokenType::STRING => {
+                    // The literal holds the unescaped contents; the token text stays as written
+                    let mut value = String::new();
+                    while !self.eof() && self.peek() != "\"" {
+                        let c = self.advance().to_string();
+                        if c == "\\" && !self.eof() {
+                            let column = self.column(self.current - 1);
+                            let escaped = self.advance().to_string();
+                            match escaped.as_str() {
+                                "n" => value.push('\n'),
+                                "t" => value.push('\t'),
+                                "\\" => value.push('\\'),
+                                "\"" => value.push('"'),
+                                _ => {
+                                    let ended_line = self.ended_line();
+                                    if ended_line {
+                                        self.new_line();
+                                    }
+                                    let line = if ended_line { self.line - 1 } else { self.line };
+                                    // Control characters, like the line break after a trailing backslash, are shown escaped
+                                    let shown: String = escaped
+                                        .chars()
+                                        .map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() })
+                                        .collect();
+                                    self.add_error_at(ErrorType::InvalidEscape(format!("\\{}", shown)), line, column);
+                                }
+                            }
+                            continue;
+                        }
+                        if self.ended_line() {
+                            self.new_line();
+                        }
+                        value.push_str(&c);
+                    }
+                    if !self.eof() {
+                        self.advance();
+                        self.add_token(lexeme, Some(Literal::String(value)));
+                    } else {
+                        // Like jlox, report where the input ran out rather than where the string opened
+                        self.add_error_at(
+                            ErrorType::UnterminatedString(self.substr(self.start, self.current).to_string()),
+                            self.line,
+                            self.column(self.current),
+                        );
+                    }
+                }
+                TokenType::NUMBER => {
+                    while Scanner::is_digit(self.peek()) {
+                        self.advance();
+                    }
+                    // Only a dot followed by a digit starts a fraction, so `123.` is NUMBER then DOT
+                    if self.peek() == "." && Scanner::is_digit(self.peek_next()) {
+                        self.advance();
+                        while Scanner::is_digit(self.peek()) {
+                            self.advance();
+                        }
+                    }
+                    self.add_token(lexeme, Some(Literal::Number(self.substr(self.start, self.current).parse::<f64>().unwrap())));
+                }
+                TokenType::WHITESPACE => { } // ignore
+                TokenType::IDENTIFIER => {
+                    while Scanner::is_identifier_char(self.peek()) {
+                        self.advance();
+                    }
+                    let word = self.substr(self.start, self.current).to_string();
+                    if let Some(keyword) = TOKEN_TYPES.get(word.as_str()) {
+                        let literal = match keyword {
+                            TokenType::TRUE => Literal::Boolean(true),
+                            TokenType::FALSE => Literal::Boolean(false),
+                            TokenType::NIL => Literal::Nil,
+                            _ => Literal::Keyword(word),
+                        };
+                        self.add_token(keyword.clone(), Some(literal));
+                    } else {
+                        self.add_token(TokenType::IDENTIFIER, Some(Literal::Identifier(word)));
+                    }
+                }
+                TokenType::ERROR => {
+                    let unexpected_char = self.substr(self.start, self.current).to_string();
+                    self.add_error(ErrorType::UnexpectedCharacter(unexpected_char));
+                }
+                _ => self.add_token(lexeme, None)
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.check_line_length(self.current);
+        self.add_token(TokenType::EOF, None);
+        self.finished = true;
+    }
+
+    // Characters are classified by their first scalar, so a grapheme like `e` plus a combining
+    // accent counts as a letter. Under scalar segmentation the lone accent is rejected.
+    fn is_beginning_identifier_char(c: &str) -> bool {
+        c.chars().next().is_some_and(char::is_alphabetic) || c == "_"
+    }
+
+    fn is_digit(c: &str) -> bool {
+        c.len() == 1 && c.as_bytes()[0].is_ascii_digit()
+    }
+
+    fn is_identifier_char(c: &str) -> bool {
+        c.chars().next().is_some_and(char::is_alphanumeric) || c == "_"
+    }
+
+    // Borrows the characters in [start, end) straight out of the source
+    fn substr(&self, start: usize, end: usize) -> &str {
+        &self.source[self.boundaries[start]..self.boundaries[end]]
+    }
+
+    // Consumes the next character, or returns "" and stays put at the end of the source
+    fn advance(&mut self) -> &str {
+        if self.eof() {
+            return "";
+        }
+        self.current += 1;
+        self.substr(self.current - 1, self.current)
+    }
+
+    // The next character, or "" at the end of the source
+    fn peek(&self) -> &str {
+        if self.eof() {
+            return "";
+        }
+        self.substr(self.current, self.current + 1)
+    }
+
+    // The character after the next one, or "" if the source ends before it
+    fn peek_next(&self) -> &str {
+        if self.current + 2 >= self.boundaries.len() {
+            return "";
+        }
+        self.substr(self.current + 1, self.current + 2)
+    }
+
+    // Closers without a matching opener and openers still open at EOF, with their lines and columns
+    pub fn unmatched_delimiters(&self) -> Vec<(TokenType, usize, usize)> {
+        let mut open: Vec<&Token> = Vec::new();
+        let mut unmatched = Vec::new();
+        for token in &self.tokens {
+            match token.token_type {
+                TokenType::LEFT_PAREN | TokenType::LEFT_BRACE => open.push(token),
+                TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACE => {
+                    let opener = match token.token_type {
+                        TokenType::RIGHT_PAREN => TokenType::LEFT_PAREN,
+                        _ => TokenType::LEFT_BRACE,
+                    };
+                    if open.last().is_some_and(|t| t.token_type == opener) {
+                        open.pop();
+                    } else {
+                        unmatched.push((token.token_type.clone(), token.line, token.column));
+                    }
+                }
+                _ => {}
+            }
+        }
+        unmatched.extend(open.into_iter().map(|t| (t.token_type.clone(), t.line, t.column)));
+        unmatched
+    }
+
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
+    }
+
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+
+    // Both at once, for callers that report errors and then use the tokens
+    pub fn into_parts(self) -> (Vec<Token>, Vec<Error>) {
+        (self.tokens, self.errors)
+    }
+
+    // The next n characters, or fewer if the source ends first
+    fn lookahead(&self, n: usize) -> &str {
+        let end = (self.current + n).min(self.boundaries.len() - 1);
+        self.substr(self.current, end)
+    }
+
+    fn is_line_break(c: &str) -> bool {
+        matches!(c, "\n" | "\r\n" | "\r")
+    }
+
+    // Whether the character just consumed ends a line. Scalar segmentation splits "\r\n" in two,
+    // so a "\r" right before a "\n" leaves the line break to the "\n".
+    fn ended_line(&self) -> bool {
+        match self.substr(self.current - 1, self.current) {
+            "\r" => self.peek() != "\n",
+            c => Scanner::is_line_break(c),
+        }
+    }
+
+    // Called once the line break ending the current line has been consumed
+    fn new_line(&mut self) {
+        // A "\r" split off from its "\n" isn't part of the line's length
+        let mut line_end = self.current - 1;
+        if line_end > self.line_start && self.substr(line_end - 1, line_end) == "\r" {
+            line_end -= 1;
+        }
+        self.check_line_length(line_end);
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn check_line_length(&mut self, line_end: usize) {
+        if let Some(max) = self.max_line_length {
+            if line_end - self.line_start > max {
+                self.warnings.push(Error {
+                    error_type: ErrorType::LineTooLong(max),
+                    line: self.line,
+                    column: max + 1,
+                });
+            }
+        }
+    }
+
+    // 1-based display column of a character on the current line. Lookups mostly move forward,
+    // so the walk resumes from the previous one instead of the start of the line.
+    fn column(&self, index: usize) -> usize {
+        let (mut at, mut column) = match self.column_cache.get() {
+            (line_start, at, column) if line_start == self.line_start && at <= index => (at, column),
+            _ => (self.line_start, 1),
+        };
+        while at < index {
+            column = if self.substr(at, at + 1) == "\t" {
+                (column - 1) / self.tab_width * self.tab_width + self.tab_width + 1
+            } else {
+                column + 1
+            };
+            at += 1;
+        }
+        self.column_cache.set((self.line_start, at, column));
+        column
+    }
+
+    // Reports the error where the current token started
+    fn add_error(&mut self, error_type: ErrorType) {
+        self.add_error_at(error_type, self.start_line, self.start_column);
+    }
+
+    fn add_error_at(&mut self, error_type: ErrorType, line: usize, column: usize) {
+        // A single lexeme (e.g. a string full of bad escapes) can report several errors
+        if self.max_errors.is_some_and(|max| self.error_count >= max) {
+            self.truncated = true;
+            return;
+        }
+        self.has_errors = true;
+        self.error_count += 1;
+        let error = Error {
+            error_type,
+            line,
+            column,
+        };
+        self.pending.push_back(Err(error));
+    }
+
+    fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
+        match token_type {
+            TokenType::FUN | TokenType::CLASS | TokenType::VAR => {
+                self.doc_comments.append(&mut self.pending_doc_comments);
+            }
+            TokenType::WHITESPACE => {}
+            _ => self.pending_doc_comments.clear(),
+        }
+        let token = if token_type == TokenType::EOF {
+            Token {
+                token_type,
+                text: String::from(""),
+                literal,
+                line: self.line,
+                column: self.column(self.current),
+                span: (self.source.len(), self.source.len()),
+            }
+        } else {
+            let text = self.substr(self.start, self.current).to_string();
+            Token {
+                token_type,
+                text,
+                literal,
+                line: self.start_line,
+                column: self.start_column,
+                span: (self.boundaries[self.start], self.boundaries[self.current]),
+            }
+        };
+        self.pending.push_back(Ok(token));
+    }
+
+    // Consumes `expected` if the source continues with it
+    fn match_next(&mut self, expected: &str) -> bool {
+        let n = expected.chars().count();
+        if self.lookahead(n) == expected {
+            self.current += n;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eof(&self) -> bool {
+        self.current == self.boundaries.len() - 1
+    }
+}
+
+// Yields tokens and errors in the order they're found, ending with the EOF token
+impl Iterator for Scanner {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.finished {
+            if self.eof() {
+                self.finish();
+            } else if self.max_errors.is_some_and(|max| self.error_count >= max) {
+                self.truncated = true;
+                self.finish();
+            } else {
+                self.scan_token();
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Scanner {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        scanner
+    }
+
+    fn token_types(scanner: &Scanner) -> Vec<TokenType> {
+        scanner.tokens.iter().map(|t| t.token_type.clone()).collect()
+    }
+
+    #[test]
+    fn scans_compound_operators() {
+        let scanner = scan("!= <= >= == ! < > =");
+        assert_eq!(token_types(&scanner), vec![
+            TokenType::BANG_EQUAL,
+            TokenType::LESS_EQUAL,
+            TokenType::GREATER_EQUAL,
+            TokenType::EQUAL_EQUAL,
+            TokenType::BANG,
+            TokenType::LESS,
+            TokenType::GREATER,
+            TokenType::EQUAL,
+            TokenType::EOF,
+        ]);
+        assert_eq!(scanner.tokens[0].text, "!=");
+        assert_eq!(scanner.tokens[1].text, "<=");
+    }
+
+    #[test]
+    fn skips_line_comments() {
+        let scanner = scan("a // ( ) \"not a string\n/b");
+        assert_eq!(token_types(&scanner), vec![
+            TokenType::IDENTIFIER,
+            TokenType::SLASH,
+            TokenType::IDENTIFIER,
+            TokenType::EOF,
+        ]);
+        assert!(!scanner.has_errors);
+    }
+
+    #[test]
+    fn reports_unterminated_strings() {
+        let scanner = scan("a\n\"abc");
+        assert!(scanner.has_errors);
+        assert_eq!(scanner.errors.len(), 1);
+        assert!(matches!(scanner.errors[0].error_type, ErrorType::UnterminatedString(_)));
+        assert_eq!(scanner.errors[0].line, 2);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn tracks_lines_across_multi_line_input() {
+        let scanner = scan("a\nb\n\n\"c\nd\" e\n");
+        let lines: Vec<usize> = scanner.tokens.iter().map(|t| t.line).collect();
+        assert_eq!(lines, vec![1, 2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn skips_block_comments() {
+        let scanner = scan("a /* ( \n ) */ b /**/ c");
+        assert_eq!(token_types(&scanner), vec![
+            TokenType::IDENTIFIER,
+            TokenType::IDENTIFIER,
+            TokenType::IDENTIFIER,
+            TokenType::EOF,
+        ]);
+        assert_eq!(scanner.tokens[1].line, 2);
+        assert!(!scanner.has_errors);
+    }
+
+    #[test]
+    fn skips_nested_block_comments() {
+        let scanner = scan("/* a /* b */ c */ d");
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(scanner.tokens[0].text, "d");
+    }
+
+    #[test]
+    fn reports_unterminated_block_comments_at_the_opening_line() {
+        let scanner = scan("a\n/* b /* c */\n\n");
+        assert!(scanner.has_errors);
+        assert!(matches!(scanner.errors[0].error_type, ErrorType::UnterminatedComment));
+        assert_eq!(scanner.errors[0].line, 2);
+    }
+
+    #[test]
+    fn unescapes_string_literals() {
+        let scanner = scan(r#""a\nb\t\\ \"q\"""#);
+        assert!(!scanner.has_errors);
+        assert_eq!(scanner.tokens[0].literal, Some(Literal::String(String::from("a\nb\t\\ \"q\""))));
+        assert_eq!(scanner.tokens[0].text, r#""a\nb\t\\ \"q\"""#);
+    }
+
+    #[test]
+    fn reports_invalid_escapes() {
+        let scanner = scan(r#""a\qb" c"#);
+        assert!(scanner.has_errors);
+        assert!(matches!(&scanner.errors[0].error_type, ErrorType::InvalidEscape(s) if s == "\\q"));
+        assert_eq!(token_types(&scanner), vec![TokenType::STRING, TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn reports_error_columns() {
+        let scanner = scan("a @ b #\n  $");
+        let positions: Vec<(usize, usize)> = scanner.errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(positions, vec![(1, 3), (1, 7), (2, 3)]);
+    }
+
+    #[test]
+    fn tracks_token_columns_per_line() {
+        let scanner = scan("var x\n  = 1;");
+        let positions: Vec<(usize, usize)> = scanner.tokens.iter().map(|t| (t.line, t.column)).collect();
+        assert_eq!(positions, vec![(1, 1), (1, 5), (2, 3), (2, 5), (2, 6), (2, 7)]);
+    }
+
+    #[test]
+    fn displays_tokens_in_codecrafters_format() {
+        let tokens = scan("( \"hi\" 12 orchid").into_tokens();
+        let lines: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+        assert_eq!(lines, vec![
+            "LEFT_PAREN ( null",
+            "STRING \"hi\" hi",
+            "NUMBER 12 12.0",
+            "IDENTIFIER orchid null",
+            "EOF  null",
+        ]);
+    }
+
+    #[test]
+    fn compares_tokens_ignoring_position() {
+        let first = scan("x").into_tokens();
+        let second = scan("\n\n x").into_tokens();
+        assert_ne!(first[0].line, second[0].line);
+        assert!(first[0].eq_ignoring_position(&second[0]));
+    }
+
+    #[test]
+    fn counts_mixed_line_endings() {
+        let source = "a\nb\r\nc\rd\r\n\r\ne";
+        for segmentation in [Segmentation::Grapheme, Segmentation::Scalar] {
+            let mut scanner = Scanner::new(source.to_string()).with_segmentation(segmentation);
+            scanner.scan_tokens();
+            assert!(!scanner.has_errors);
+            let lines: Vec<usize> = scanner.tokens.iter().map(|t| t.line).collect();
+            assert_eq!(lines, vec![1, 2, 3, 4, 6, 6]);
+        }
+    }
+
+    #[test]
+    fn ends_line_comments_at_carriage_returns() {
+        let scanner = scan("// note\r\nx // more\ry");
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(scanner.tokens[1].line, 3);
+    }
+
+    #[test]
+    fn attaches_boolean_and_nil_literals() {
+        let scanner = scan("true false nil and");
+        let literals: Vec<Option<Literal>> = scanner.tokens.iter().map(|t| t.literal.clone()).collect();
+        assert_eq!(literals, vec![
+            Some(Literal::Boolean(true)),
+            Some(Literal::Boolean(false)),
+            Some(Literal::Nil),
+            Some(Literal::Keyword(String::from("and"))),
+            None,
+        ]);
+        let shown: Vec<String> = literals.iter().flatten().map(|l| l.to_string()).collect();
+        assert_eq!(shown, vec!["true", "false", "nil", "null"]);
+        assert_eq!(scanner.tokens[0].to_string(), "TRUE true null");
+    }
+
+    #[test]
+    fn serializes_tokens_and_errors_as_json() {
+        let scanner = scan("\"hi\" @");
+        assert_eq!(
+            scanner.tokens[0].json_fields(),
+            "\"type\":\"STRING\",\"lexeme\":\"\\\"hi\\\"\",\"literal\":\"hi\",\"line\":1"
+        );
+        assert_eq!(scanner.errors[0].json_fields(), "\"message\":\"Unexpected character: @\",\"line\":1,\"column\":6");
+    }
+
+    #[test]
+    fn stops_cleanly_at_end_of_source() {
+        for source in ["\"", "/*", "x = \"", "\"\\", "\"\"\"", "1.", "//"] {
+            for segmentation in [Segmentation::Grapheme, Segmentation::Scalar] {
+                let mut scanner = Scanner::new(source.to_string()).with_segmentation(segmentation);
+                scanner.scan_tokens();
+                assert_eq!(scanner.tokens.last().map(|t| &t.token_type), Some(&TokenType::EOF), "{:?}", source);
+            }
+        }
+        assert!(matches!(scan("\"").errors[0].error_type, ErrorType::UnterminatedString(_)));
+        assert!(matches!(scan("/*").errors[0].error_type, ErrorType::UnterminatedComment));
+    }
+
+    #[test]
+    fn scans_unicode_identifiers() {
+        let scanner = scan("var résumé = naïve;");
+        assert!(!scanner.has_errors);
+        let texts: Vec<&str> = scanner.tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["var", "résumé", "=", "naïve", ";", ""]);
+        assert_eq!(scanner.tokens[1].token_type, TokenType::IDENTIFIER);
+    }
+
+    #[test]
+    fn keeps_combining_characters_in_identifiers() {
+        // "cafe" followed by U+0301 COMBINING ACUTE ACCENT, one grapheme with the "e"
+        let scanner = scan("cafe\u{301} x");
+        assert!(!scanner.has_errors);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(scanner.tokens[0].text, "cafe\u{301}");
+        assert_eq!(scanner.tokens[1].column, 6);
+
+        // Scalar segmentation splits the accent off its "e", and on its own it is not a letter
+        let mut scalar = Scanner::new(String::from("cafe\u{301}")).with_segmentation(Segmentation::Scalar);
+        scalar.scan_tokens();
+        assert!(scalar.has_errors);
+    }
+
+    #[test]
+    fn round_trips_operators_through_the_lexeme_table() {
+        for (token_type, lexeme) in LEXEMES.iter().filter(|(_, l)| (1..=2).contains(&l.len()) && !l.starts_with(char::is_alphabetic)) {
+            assert_eq!(TokenType::parse(lexeme).as_ref(), Some(token_type), "{:?}", lexeme);
+            assert_eq!(TOKENS[token_type], *lexeme);
+        }
+        assert_eq!(TokenType::parse(""), Some(TokenType::EOF));
+    }
+
+    #[test]
+    fn spans_index_the_source_in_bytes() {
+        let source = "var café = \"naïve ☃\";\n// ü\nprint café;";
+        for segmentation in [Segmentation::Grapheme, Segmentation::Scalar] {
+            let mut scanner = Scanner::new(source.to_string()).with_segmentation(segmentation);
+            scanner.scan_tokens();
+            for token in &scanner.tokens {
+                assert_eq!(&source[token.span.0..token.span.1], token.text);
+            }
+            assert_eq!(scanner.tokens[1].span, (4, 9));
+        }
+    }
+
+    #[test]
+    fn stops_after_max_errors() {
+        let mut scanner = Scanner::new(String::from("@ # $ % ^")).with_max_errors(2);
+        scanner.scan_tokens();
+        assert!(scanner.truncated);
+        assert_eq!(scanner.errors.len(), 2);
+        assert_eq!(token_types(&scanner), vec![TokenType::EOF]);
+
+        let mut scanner = Scanner::new(String::from("a @ b")).with_max_errors(2);
+        scanner.scan_tokens();
+        assert!(!scanner.truncated);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn matches_tokens_by_type_and_literal() {
+        let scanner = scan("\n  \"hi\" 2 +");
+        let tokens = &scanner.tokens;
+        assert!(tokens[0].matches(&TokenType::STRING, Some(&Literal::String(String::from("hi")))));
+        assert!(tokens[1].matches(&TokenType::NUMBER, Some(&Literal::Number(2.0))));
+        assert!(tokens[2].matches(&TokenType::PLUS, None));
+        assert!(!tokens[1].matches(&TokenType::NUMBER, Some(&Literal::Number(3.0))));
+        assert!(!tokens[0].matches(&TokenType::STRING, None));
+    }
+
+    #[test]
+    fn expands_tabs_to_tab_stops_in_columns() {
+        let source = "a\t b\t\t@\n  \t@";
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let columns: Vec<usize> = scanner.tokens.iter().map(|t| t.column).collect();
+        assert_eq!(columns, vec![1, 10, 10]);
+        let errors: Vec<(usize, usize)> = scanner.errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(errors, vec![(1, 25), (2, 9)]);
+
+        let mut scanner = Scanner::new(source.to_string()).with_tab_width(4);
+        scanner.scan_tokens();
+        let errors: Vec<(usize, usize)> = scanner.errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(errors, vec![(1, 13), (2, 5)]);
+    }
+
+    #[test]
+    fn streams_the_same_items_as_scan_tokens() {
+        let source = "var x = \"a\\qb\" @ 12.5;\n/* c */ x >= 1 # \"open";
+        let eager = scan(source);
+        let (mut tokens, mut errors) = (Vec::new(), Vec::new());
+        for item in Scanner::new(source.to_string()) {
+            match item {
+                Ok(token) => tokens.push(format!("{:?}", token)),
+                Err(error) => errors.push(format!("{:?}", error)),
+            }
+        }
+        assert_eq!(tokens, eager.tokens.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>());
+        assert_eq!(errors, eager.errors.iter().map(|e| format!("{:?}", e)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn scans_lazily() {
+        let mut scanner = Scanner::new(String::from("a @ b"));
+        assert!(matches!(scanner.next(), Some(Ok(token)) if token.text == "a"));
+        assert_eq!(scanner.current, 1);
+        assert!(matches!(scanner.next(), Some(Err(_))));
+        assert!(matches!(scanner.next(), Some(Ok(token)) if token.text == "b"));
+        assert!(matches!(scanner.next(), Some(Ok(token)) if token.token_type == TokenType::EOF));
+        assert!(scanner.next().is_none());
+    }
+
+    #[test]
+    fn substr_borrows_from_the_source() {
+        let scanner = scan("  orchid");
+        let slice = scanner.substr(2, 8);
+        assert_eq!(slice, "orchid");
+        let source = scanner.source.as_bytes().as_ptr_range();
+        let borrowed = slice.as_bytes().as_ptr_range();
+        assert!(source.start <= borrowed.start && borrowed.end <= source.end);
+    }
+
+    #[test]
+    fn segmentation_decides_what_counts_as_a_character() {
+        // Thumbs up plus a skin tone modifier: one grapheme, two scalars
+        let source = "\"👍🏽\" x";
+        let mut grapheme = Scanner::new(source.to_string()).with_segmentation(Segmentation::Grapheme);
+        grapheme.scan_tokens();
+        let mut scalar = Scanner::new(source.to_string()).with_segmentation(Segmentation::Scalar);
+        scalar.scan_tokens();
+        assert_eq!(grapheme.boundaries.len() - 1, 5);
+        assert_eq!(scalar.boundaries.len() - 1, 6);
+        assert_eq!(grapheme.tokens[1].column, 5);
+        assert_eq!(scalar.tokens[1].column, 6);
+        assert_eq!(grapheme.tokens[0].text, scalar.tokens[0].text);
+    }
+
+    #[test]
+    fn scans_heredocs_verbatim() {
+        let scanner = scan("x\n\"\"\"a\n\\n b\"\"\" y");
+        assert!(!scanner.has_errors);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::STRING, TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(scanner.tokens[1].literal, Some(Literal::String(String::from("a\n\\n b"))));
+        assert_eq!(scanner.tokens[1].line, 2);
+        assert_eq!(scanner.tokens[2].line, 3);
+    }
+
+    #[test]
+    fn reports_unterminated_heredocs() {
+        let scanner = scan("\"\"\"never\nclosed\"\"");
+        assert!(matches!(&scanner.errors[..], [Error { error_type: ErrorType::UnterminatedString(_), line: 2, .. }]));
+        assert_eq!(token_types(&scanner), vec![TokenType::EOF]);
+    }
+
+    #[test]
+    fn offsets_lines_from_the_start_line() {
+        let mut scanner = Scanner::new(String::from("@ a\nb")).with_start_line(100);
+        scanner.scan_tokens();
+        assert_eq!(scanner.errors[0].line, 100);
+        let lines: Vec<usize> = scanner.tokens.iter().map(|t| t.line).collect();
+        assert_eq!(lines, vec![100, 101, 101]);
+    }
+
+    #[test]
+    fn warns_about_long_lines_only_when_asked() {
+        let source = "a2345\r\nb23456\nc\nd234567";
+        assert!(scan(source).warnings.is_empty());
+        for segmentation in [Segmentation::Grapheme, Segmentation::Scalar] {
+            let mut scanner = Scanner::new(source.to_string()).with_segmentation(segmentation).with_max_line_length(5);
+            scanner.scan_tokens();
+            let lines: Vec<usize> = scanner.warnings.iter().map(|w| w.line).collect();
+            assert_eq!(lines, vec![2, 4]);
+            assert!(matches!(scanner.warnings[0].error_type, ErrorType::LineTooLong(5)));
+        }
+    }
+
+    #[test]
+    fn finds_unmatched_delimiters() {
+        assert_eq!(scan("a) }").unmatched_delimiters(), vec![
+            (TokenType::RIGHT_PAREN, 1, 2),
+            (TokenType::RIGHT_BRACE, 1, 4),
+        ]);
+        assert_eq!(scan("{ (x) }\n({").unmatched_delimiters(), vec![
+            (TokenType::LEFT_PAREN, 2, 1),
+            (TokenType::LEFT_BRACE, 2, 2),
+        ]);
+    }
+
+    #[test]
+    fn preprocess_keeps_defined_blocks() {
+        let source = "a\n//#if DEBUG\nb\n//#endif\nc";
+        assert_eq!(preprocess(source, &[String::from("DEBUG")]).unwrap(), source);
+    }
+
+    #[test]
+    fn preprocess_blanks_undefined_blocks() {
+        let source = "a\n//#if DEBUG\nb\r\nc\n//#endif\nd";
+        assert_eq!(preprocess(source, &[]).unwrap(), "a\n//#if DEBUG\n\r\n\n//#endif\nd");
+    }
+
+    #[test]
+    fn preprocess_rejects_malformed_directives() {
+        let error = preprocess("a\n//#if DEBUG\nb", &[]).unwrap_err();
+        assert!(matches!(error, Error { error_type: ErrorType::UnterminatedDirective, line: 2, .. }));
+        let error = preprocess("//#if A\n//#if B\n//#endif\n//#endif", &[]).unwrap_err();
+        assert!(matches!(error, Error { error_type: ErrorType::NestedDirective, line: 2, .. }));
+        let error = preprocess("a\n\n//#endif", &[]).unwrap_err();
+        assert!(matches!(error, Error { error_type: ErrorType::UnmatchedEndif, line: 3, .. }));
+    }
+
+    #[test]
+    fn collects_doc_comments_before_declarations() {
+        let source = "/// Adds one.\nfun inc() {}\n//// banner\n/// Dropped.\nx;\n/// Kept.\n// plain\nvar y;";
+        let mut scanner = Scanner::new(source.to_string()).with_doc_comments();
+        scanner.scan_tokens();
+        let docs: Vec<&str> = scanner.doc_comments.iter().map(|(_, doc)| doc.as_str()).collect();
+        assert_eq!(docs, vec!["Adds one.", "Kept."]);
+        let (start, end) = scanner.doc_comments[0].0;
+        assert_eq!(&source[start..end], "/// Adds one.");
+    }
+
+    #[test]
+    fn skips_form_feeds_and_vertical_tabs() {
+        let scanner = scan("a\u{000C}b\u{000B}c");
+        assert!(!scanner.has_errors);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn keeps_whitespace_runs_as_tokens() {
+        let mut scanner = Scanner::new(String::from("a \t\n b")).with_whitespace_tokens();
+        scanner.scan_tokens();
+        let tokens: Vec<(TokenType, &str, usize)> = scanner.tokens.iter().map(|t| (t.token_type.clone(), t.text.as_str(), t.line)).collect();
+        assert_eq!(tokens, vec![
+            (TokenType::IDENTIFIER, "a", 1),
+            (TokenType::WHITESPACE, " \t\n ", 1),
+            (TokenType::IDENTIFIER, "b", 2),
+            (TokenType::EOF, "", 2),
+        ]);
+        assert_eq!(token_types(&scan("a \t\n b")), vec![TokenType::IDENTIFIER, TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn caps_errors_within_a_single_lexeme() {
+        let mut scanner = Scanner::new(String::from("\"\\q\\q\\q\" x")).with_max_errors(1);
+        scanner.scan_tokens();
+        assert!(scanner.truncated);
+        assert_eq!(scanner.errors.len(), 1);
+        assert_eq!(token_types(&scanner), vec![TokenType::STRING, TokenType::EOF]);
+    }
+
+    #[test]
+    fn treats_a_zero_error_limit_as_unlimited() {
+        let mut scanner = Scanner::new(String::from("@ # x")).with_max_errors(0);
+        scanner.scan_tokens();
+        assert!(!scanner.truncated);
+        assert_eq!(scanner.errors.len(), 2);
+        assert_eq!(token_types(&scanner), vec![TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn hands_over_tokens_and_errors() {
+        let tokens: Vec<Token> = scan("x = 1").into_tokens();
+        assert_eq!(tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["x", "=", "1", ""]);
+
+        let errors: Vec<Error> = scan("@ x #").into_errors();
+        assert_eq!(errors.iter().map(|e| e.column).collect::<Vec<_>>(), vec![1, 5]);
+
+        let (tokens, errors) = scan("@ x").into_parts();
+        assert_eq!(tokens.into_iter().map(|t| t.token_type).collect::<Vec<_>>(), vec![TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(errors.len(), 1);
+    }
+
+
+    #[test]
+    fn writes_overflowing_numbers_as_json_null() {
+        let source = "9".repeat(400);
+        let tokens = scan(&source).into_tokens();
+        assert_eq!(tokens[0].literal, Some(Literal::Number(f64::INFINITY)));
+        assert!(tokens[0].json_fields().contains("\"literal\":null,"), "{}", tokens[0].json_fields());
+    }
+
+
+    #[test]
+    fn leaves_a_trailing_dot_out_of_a_number() {
+        let scanner = scan("123. 45.67");
+        let listing: Vec<String> = scanner.tokens.iter().map(Token::to_string).collect();
+        assert_eq!(listing, vec!["NUMBER 123 123.0", "DOT . null", "NUMBER 45.67 45.67", "EOF  null"]);
+    }
+
+
+    #[test]
+    fn escapes_control_characters_in_invalid_escapes() {
+        let scanner = scan("\"a\\\nb\" \"c\\\r\nd\"");
+        let messages: Vec<String> = scanner.errors.iter().map(|e| e.error_type.to_string()).collect();
+        assert_eq!(messages, vec!["Invalid escape sequence: \\\\n", "Invalid escape sequence: \\\\r\\n"]);
+        assert_eq!((scanner.errors[1].line, scanner.errors[1].column), (2, 6));
+    }
+}
@@ -0,0 +1,9 @@
+use crate::Token;
+use crate::expr::Expr;
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    Var(Token, Option<Expr>), // name, initializer
+}
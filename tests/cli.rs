@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn interpreter(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// Writes source to a file unique to the calling test, returning its path
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rusty-lox-{}-{}.lox", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn only_errors_leaves_stdout_empty() {
+    let path = source_file("only-errors", "@ #\n$");
+    let output = interpreter(&["tokenize", "--only-errors", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unexpected character: @"));
+}
+
+#[test]
+fn rejects_unknown_flags() {
+    let output = interpreter(&["tokenize", "--jsn", "-c", "1"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown flag: --jsn"));
+}
+
+#[test]
+fn rejects_a_second_source_file() {
+    let output = interpreter(&["tokenize", "a.lox", "b.lox"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unexpected argument: b.lox"));
+}
+
+#[test]
+fn offsets_directive_errors_by_the_start_line() {
+    let output = interpreter(&["tokenize", "--start-line", "100", "-c", "//#if DEBUG\nx"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("[line 100:1] Error: Unterminated '#if' block."));
+}
+
+#[test]
+fn runs_inline_source() {
+    let output = interpreter(&["run", "-c", "print 2*3;"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n");
+}
+
+#[test]
+fn names_the_command_line_in_inline_errors() {
+    let output = interpreter(&["run", "-e", "print 1 @"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "<command-line>: [line 1:9] Error: Unexpected character: @\n");
+
+    let output = interpreter(&["run", "-c", "print nope;"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("<command-line>: Undefined variable 'nope'."));
+}
+
+#[test]
+fn keeps_whitespace_only_for_tokenize() {
+    let output = interpreter(&["parse", "--keep-whitespace", "-c", "1 + 2"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+
+    let output = interpreter(&["tokenize", "--keep-whitespace", "-c", "1 +"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "NUMBER 1 1.0\nWHITESPACE   null\nPLUS + null\nEOF  null\n");
+}
+
+#[test]
+fn rejects_a_zero_error_limit() {
+    let output = interpreter(&["tokenize", "--max-errors", "0", "-c", "@"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--max-errors expects a positive number of errors"));
+}
+
+#[test]
+fn exits_64_on_every_usage_error() {
+    for args in [
+        &["tokenize"][..],
+        &["tokenize", "--segmentation", "bytes", "-c", "1"],
+        &["tokenize", "--start-line", "x", "-c", "1"],
+        &["tokenize", "--tab-width"],
+        &["tokenize", "-c"],
+        &["tokenize", "--only-errors"],
+        &["format", "-c", "1"],
+    ] {
+        let output = interpreter(args);
+        assert_eq!(output.status.code(), Some(64), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+    }
+}
+
+#[test]
+fn rejects_conflicting_listings() {
+    let output = interpreter(&["tokenize", "--json", "--count", "-c", "1"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--count can't be combined with --json"));
+
+    let output = interpreter(&["tokenize", "--count", "--count", "-c", "1"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn reports_scan_errors_from_the_start_line() {
+    let path = source_file("start-line", "@\nx");
+    let output = interpreter(&["tokenize", "--start-line", "100", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(String::from_utf8_lossy(&output.stderr).lines().next(), Some("[line 100:1] Error: Unexpected character: @"));
+
+    let output = interpreter(&["tokenize", "--start-line", "0", "-c", "@"]);
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn interleaves_errors_only_on_stdout() {
+    let output = interpreter(&["tokenize", "--interleave", "-c", "a @"]);
+    assert_eq!(output.status.code(), Some(65));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "IDENTIFIER a null\n<command-line>: [line 1:3] Error: Unexpected character: @\nEOF  null\n");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Unexpected character"));
+}